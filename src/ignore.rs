@@ -0,0 +1,192 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_supports_star_and_double_star() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(!glob_match("*.log", "debug.log.txt"));
+        assert!(glob_match("**/build", "a/b/build"));
+        assert!(!glob_match("*.log", "a/debug.log"));
+    }
+
+    #[test]
+    fn parse_patterns_skips_comments_and_blank_lines() {
+        let patterns = parse_patterns("# comment\n\ntarget/\n!target/keep.txt\n");
+        assert_eq!(patterns.len(), 2);
+        assert!(patterns[0].dir_only);
+        assert!(patterns[1].negated);
+    }
+
+    #[test]
+    fn last_match_wins_across_layers() {
+        let root = PathBuf::from("/repo");
+        let sub = PathBuf::from("/repo/sub");
+        let stack = IgnoreStack::root()
+            .push(root, parse_patterns("*.log\n"))
+            .push(sub.clone(), parse_patterns("!keep.log\n"));
+
+        assert!(stack.is_ignored(&sub.join("debug.log"), false));
+        assert!(!stack.is_ignored(&sub.join("keep.log"), false));
+    }
+}
+
+/// A single parsed line from a `.gitignore` file.
+pub struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str) -> Option<IgnorePattern> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut pattern = line;
+        let negated = pattern.starts_with('!');
+        if negated {
+            pattern = &pattern[1..];
+        }
+        let dir_only = pattern.ends_with('/');
+        if dir_only {
+            pattern = &pattern[..pattern.len() - 1];
+        }
+        let anchored = pattern.starts_with('/');
+        if anchored {
+            pattern = &pattern[1..];
+        }
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(IgnorePattern {
+            glob: pattern.to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Tests `relative_path` (relative to the directory this pattern was parsed in) against the
+    /// pattern, given whether the candidate itself is a directory.
+    fn matches(&self, relative_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored || self.glob.contains('/') {
+            glob_match(&self.glob, relative_path)
+        } else {
+            relative_path
+                .split('/')
+                .any(|segment| glob_match(&self.glob, segment))
+        }
+    }
+}
+
+/// Parses a `.gitignore` file's contents into an ordered list of patterns.
+pub fn parse_patterns(contents: &str) -> Vec<IgnorePattern> {
+    contents.lines().filter_map(IgnorePattern::parse).collect()
+}
+
+/// Matches `glob` (supporting `*`, `**` and `?`) against `candidate`. `*` doesn't cross `/`
+/// boundaries, `**` does, and `?` matches any single non-`/` character.
+fn glob_match(glob: &str, candidate: &str) -> bool {
+    glob_match_bytes(glob.as_bytes(), candidate.as_bytes())
+}
+
+fn glob_match_bytes(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = &pattern[2..];
+            (0..=text.len()).any(|i| glob_match_bytes(rest, &text[i..]))
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            for (i, &byte) in text.iter().enumerate() {
+                if glob_match_bytes(rest, &text[i..]) {
+                    return true;
+                }
+                if byte == b'/' {
+                    return false;
+                }
+            }
+            glob_match_bytes(rest, &[])
+        }
+        Some(b'?') => match text.first() {
+            Some(&b'/') | None => false,
+            Some(_) => glob_match_bytes(&pattern[1..], &text[1..]),
+        },
+        Some(&c) => match text.first() {
+            Some(&byte) if byte == c => glob_match_bytes(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+    }
+}
+
+/// One directory's worth of `.gitignore` patterns, anchored at the directory they were parsed
+/// in, linked back to the patterns collected from its ancestors.
+struct IgnoreLayer {
+    parent: Option<Arc<IgnoreLayer>>,
+    root: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+/// The accumulated `.gitignore` pattern stack inherited while descending into a directory tree.
+/// Cloning is cheap (an `Arc` bump) so each queued subdirectory can carry its own stack.
+#[derive(Clone)]
+pub struct IgnoreStack {
+    layer: Option<Arc<IgnoreLayer>>,
+}
+
+impl IgnoreStack {
+    pub fn root() -> Self {
+        IgnoreStack { layer: None }
+    }
+
+    /// Returns a new stack with `patterns` parsed from the `.gitignore` found in `root` layered
+    /// on top of this one.
+    pub fn push(&self, root: PathBuf, patterns: Vec<IgnorePattern>) -> IgnoreStack {
+        IgnoreStack {
+            layer: Some(Arc::new(IgnoreLayer {
+                parent: self.layer.clone(),
+                root,
+                patterns,
+            })),
+        }
+    }
+
+    /// Tests `path` against every layer in the stack from the outermost directory to the
+    /// innermost, with later (more specific) matches overriding earlier ones.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut layers = Vec::new();
+        let mut current = self.layer.as_ref();
+        while let Some(layer) = current {
+            layers.push(layer.as_ref());
+            current = layer.parent.as_ref();
+        }
+
+        let mut ignored = false;
+        for layer in layers.into_iter().rev() {
+            let Ok(relative) = path.strip_prefix(&layer.root) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            for pattern in &layer.patterns {
+                if pattern.matches(&relative, is_dir) {
+                    ignored = !pattern.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
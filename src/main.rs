@@ -1,4 +1,18 @@
-use std::{env, fs, path::PathBuf, sync::mpsc, thread};
+mod ignore;
+
+use std::{
+    collections::VecDeque,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use ignore::IgnoreStack;
 
 #[cfg(test)]
 mod tests {
@@ -14,16 +28,235 @@ mod tests {
         // Code below panics as unwrap() is called on the Err variant
         Config::parse(args.into_iter()).unwrap();
     }
+
+    #[test]
+    fn extract_flag_value_removes_flag_and_value() {
+        let mut args = vec![
+            String::from("path"),
+            String::from("--threads"),
+            String::from("4"),
+            String::from("query"),
+        ];
+        let value = extract_flag_value(&mut args, "--threads");
+        assert_eq!(value, Some(String::from("4")));
+        assert_eq!(args, vec![String::from("path"), String::from("query")]);
+    }
+
+    #[test]
+    fn print_sorted_drains_buffer() {
+        let mut buffer = vec![
+            Match {
+                path: PathBuf::from("b"),
+                location: MatchLocation::Line {
+                    line_no: 1,
+                    line: String::new(),
+                },
+            },
+            Match {
+                path: PathBuf::from("a"),
+                location: MatchLocation::Line {
+                    line_no: 1,
+                    line: String::new(),
+                },
+            },
+        ];
+        print_sorted(&mut buffer, false);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn matcher_substring_matches_contained_text() {
+        let matcher = Matcher::Substring(String::from("needle"));
+        assert!(matcher.is_match("a needle in a haystack"));
+        assert!(!matcher.is_match("nothing here"));
+    }
+
+    #[test]
+    fn matcher_regex_matches_pattern() {
+        let matcher = Matcher::Regex(regex::bytes::Regex::new(r"^\d+$").unwrap());
+        assert!(matcher.is_match("1234"));
+        assert!(!matcher.is_match("12a4"));
+    }
+
+    #[test]
+    fn file_type_parses_known_values() {
+        assert!(matches!(FileType::parse("f"), Ok(FileType::File)));
+        assert!(matches!(FileType::parse("d"), Ok(FileType::Directory)));
+        assert!(matches!(FileType::parse("l"), Ok(FileType::Symlink)));
+        assert!(FileType::parse("x").is_err());
+    }
+
+    #[test]
+    fn is_binary_detects_nul_byte() {
+        assert!(is_binary(b"plain\0text"));
+        assert!(!is_binary(b"plain text"));
+    }
+
+    #[test]
+    fn find_byte_offsets_locates_every_occurrence() {
+        assert_eq!(find_byte_offsets(b"abcabcabc", b"abc"), vec![0, 3, 6]);
+        assert_eq!(find_byte_offsets(b"abc", b"xyz"), Vec::<usize>::new());
+    }
 }
 
 struct Config {
     path: PathBuf,
-    query: String,
+    matcher: Matcher,
+    threads: usize,
+    max_buffer_time: Duration,
+    line_number: bool,
+    no_ignore: bool,
+    hidden: bool,
+    file_type: Option<FileType>,
+    max_depth: Option<usize>,
+    search_binary: bool,
+}
+
+/// The kind of filesystem entry a match is restricted to by `--type`, modeled on fd's `FileType`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileType {
+    File,
+    Directory,
+    Symlink,
+}
+
+impl FileType {
+    fn parse(value: &str) -> Result<FileType, &'static str> {
+        match value {
+            "f" => Ok(FileType::File),
+            "d" => Ok(FileType::Directory),
+            "l" => Ok(FileType::Symlink),
+            _ => Err("Invalid value for --type (expected f, d, or l)"),
+        }
+    }
+}
+
+/// Default amount of time the receiver buffers matches before giving up on finishing the whole
+/// search quickly and switching to printing results as they arrive.
+const DEFAULT_MAX_BUFFER_TIME_MS: u64 = 100;
+
+/// How a line is tested against the query: plain substring search, or a compiled regex.
+enum Matcher {
+    Substring(String),
+    Regex(regex::bytes::Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Matcher::Substring(query) => line.contains(query.as_str()),
+            Matcher::Regex(re) => re.is_match(line.as_bytes()),
+        }
+    }
+
+    /// Finds every offset `self` matches at within raw `haystack` bytes. Used for binary files,
+    /// where there's no notion of a "line" to report instead.
+    fn byte_offsets(&self, haystack: &[u8]) -> Vec<usize> {
+        match self {
+            Matcher::Substring(query) => find_byte_offsets(haystack, query.as_bytes()),
+            Matcher::Regex(re) => re.find_iter(haystack).map(|m| m.start()).collect(),
+        }
+    }
+}
+
+/// Number of leading bytes sniffed to decide whether a file is binary, mirroring ripgrep's
+/// heuristic: the presence of a NUL byte.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// A memchr-accelerated search for every offset `needle` occurs at within `haystack`: jump to
+/// the next occurrence of the needle's first byte, then verify the rest matches.
+fn find_byte_offsets(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    let Some(&first_byte) = needle.first() else {
+        return Vec::new();
+    };
+
+    let mut offsets = Vec::new();
+    let mut start = 0;
+    while start + needle.len() <= haystack.len() {
+        match memchr::memchr(
+            first_byte,
+            &haystack[start..haystack.len() - needle.len() + 1],
+        ) {
+            Some(relative) => {
+                let candidate = start + relative;
+                if &haystack[candidate..candidate + needle.len()] == needle {
+                    offsets.push(candidate);
+                }
+                start = candidate + 1;
+            }
+            None => break,
+        }
+    }
+    offsets
+}
+
+/// Removes `flag` and the value immediately following it from `args`, returning that value.
+fn extract_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+    args.remove(idx);
+    if idx < args.len() {
+        Some(args.remove(idx))
+    } else {
+        None
+    }
+}
+
+/// Removes a boolean `flag` from `args` if present, returning whether it was there.
+fn extract_flag_present(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|arg| arg == flag) {
+        Some(idx) => {
+            args.remove(idx);
+            true
+        }
+        None => false,
+    }
 }
 
 impl Config {
-    fn parse(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
-        args.next(); // skip first arguments as it's just a name of the program
+    fn parse(args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
+        let mut args: Vec<String> = args.collect();
+        args.remove(0); // skip first arguments as it's just a name of the program
+
+        let threads = match extract_flag_value(&mut args, "--threads") {
+            Some(value) => match value.parse::<usize>() {
+                Ok(0) | Err(_) => return Err("Invalid value for --threads"),
+                Ok(threads) => threads,
+            },
+            None => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+        };
+        let max_buffer_time = match extract_flag_value(&mut args, "--max-buffer-time") {
+            Some(value) => Duration::from_millis(
+                value
+                    .parse::<u64>()
+                    .map_err(|_| "Invalid value for --max-buffer-time")?,
+            ),
+            None => Duration::from_millis(DEFAULT_MAX_BUFFER_TIME_MS),
+        };
+        let use_regex = extract_flag_present(&mut args, "--regex");
+        let line_number = extract_flag_present(&mut args, "--line-number");
+        let no_ignore = extract_flag_present(&mut args, "--no-ignore");
+        let hidden = extract_flag_present(&mut args, "--hidden");
+        let search_binary = extract_flag_present(&mut args, "--text");
+        let file_type = match extract_flag_value(&mut args, "--type") {
+            Some(value) => Some(FileType::parse(&value)?),
+            None => None,
+        };
+        let max_depth = match extract_flag_value(&mut args, "--max-depth") {
+            Some(value) => Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| "Invalid value for --max-depth")?,
+            ),
+            None => None,
+        };
+
+        let mut args = args.into_iter();
         match args.next() {
             Some(path) => match args.next() {
                 Some(query) => {
@@ -31,111 +264,416 @@ impl Config {
                         Ok(result) => result,
                         Err(_) => return Err("Given path is not valid"),
                     };
-                    Ok(Config { path, query })
+                    let matcher = if use_regex {
+                        Matcher::Regex(
+                            regex::bytes::Regex::new(&query).map_err(|_| "Invalid regex query")?,
+                        )
+                    } else {
+                        Matcher::Substring(query)
+                    };
+                    Ok(Config {
+                        path,
+                        matcher,
+                        threads,
+                        max_buffer_time,
+                        line_number,
+                        no_ignore,
+                        hidden,
+                        file_type,
+                        max_depth,
+                        search_binary,
+                    })
                 }
-                None => return Err("No text to search for. Usage: cargo run -- <PATH> <QUERY>"),
+                None => Err("No text to search for. Usage: cargo run -- <PATH> <QUERY>"),
             },
-            None => return Err("No file path found. Usage: cargo run -- <PATH> <QUERY>"),
-        }
-    }
-}
-
-/// Recursively searches given path for the files that contain given query and sends them through
-/// Sender channel
-fn search_in_path(path: &PathBuf, query: String, tx: mpsc::Sender<String>) {
-    if path.is_dir() {
-        let mut handles = vec![];
-        // Here we create inner block so our paths variable goes out of scope and frees resources,
-        // preventing from deadlock when we iterate over a big directory and reach maximum amount
-        // of open files and all of them are directories (read_dir creates file handle)
-        {
-            // We need this loop because sometimes there will be an os error 24 (too many open file) when searching in big directories
-            // And we will have to wait for some thread to finish and free the resources
-            // If we don't use loop here - some directories won't be scanned due to aforementioned
-            // error and this will result in an undefined behaviour
-            let paths = loop {
-                match fs::read_dir(path) {
-                    Ok(paths) => break paths,
-                    Err(e) => match e.kind() {
-                        std::io::ErrorKind::PermissionDenied => {
-                            println!("Permission denied: {}", path.to_string_lossy());
-                            return;
-                        }
-                        _ => {
-                            // Too many open files -> wait for 1000 nanoseconds and retry
-                            if e.to_string().contains("os error 24") {
-                                std::thread::sleep(std::time::Duration::from_nanos(1000));
+            None => Err("No file path found. Usage: cargo run -- <PATH> <QUERY>"),
+        }
+    }
+}
+
+/// A path queued up for a worker to visit, along with the `.gitignore` pattern stack inherited
+/// from its ancestors (needed to decide whether its own children should be filtered out) and its
+/// depth relative to the search root (needed to enforce `--max-depth`).
+struct WorkItem {
+    path: PathBuf,
+    ignore_stack: IgnoreStack,
+    depth: usize,
+}
+
+/// A shared queue of paths still waiting to be visited, plus a count of paths that are queued
+/// or currently being processed by a worker. Workers rely on that count (rather than the queue
+/// merely being empty) to tell "momentarily empty" apart from "truly drained".
+struct WorkQueue {
+    items: Mutex<VecDeque<WorkItem>>,
+    condvar: Condvar,
+    pending: AtomicUsize,
+}
+
+impl WorkQueue {
+    fn new(root: PathBuf) -> Self {
+        let root = WorkItem {
+            path: root,
+            ignore_stack: IgnoreStack::root(),
+            depth: 0,
+        };
+        WorkQueue {
+            items: Mutex::new(VecDeque::from([root])),
+            condvar: Condvar::new(),
+            pending: AtomicUsize::new(1),
+        }
+    }
+
+    /// Queues up a new item for the workers to pick up.
+    fn push(&self, item: WorkItem) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.items.lock().unwrap().push_back(item);
+        self.condvar.notify_one();
+    }
+
+    /// Blocks until an item is available or the queue is truly drained (`None`).
+    fn pop(&self) -> Option<WorkItem> {
+        let mut items = self.items.lock().unwrap();
+        loop {
+            if let Some(item) = items.pop_front() {
+                return Some(item);
+            }
+            if self.pending.load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+            items = self.condvar.wait(items).unwrap();
+        }
+    }
+
+    /// Marks a previously popped item as fully processed (its children, if any, have already
+    /// been pushed back onto the queue).
+    fn finish(&self) {
+        // Hold the lock so a worker in `pop` can't miss this wakeup between its `pending` check
+        // and its `wait`.
+        let _items = self.items.lock().unwrap();
+        self.pending.fetch_sub(1, Ordering::SeqCst);
+        self.condvar.notify_all();
+    }
+}
+
+/// Where in a file a match was found: a 1-based line for text files, or a raw byte offset for
+/// binary files (which have no meaningful line structure).
+enum MatchLocation {
+    Line { line_no: usize, line: String },
+    ByteOffset(usize),
+}
+
+/// A match found in a searched file, with enough context to print a grep-style result.
+struct Match {
+    path: PathBuf,
+    location: MatchLocation,
+}
+
+/// Builds the `.gitignore` pattern stack that `path`'s own children should be tested against,
+/// by parsing `path/.gitignore` (if any) and layering it on top of the inherited `ignore_stack`.
+fn child_ignore_stack(path: &Path, ignore_stack: &IgnoreStack) -> IgnoreStack {
+    match fs::read_to_string(path.join(".gitignore")) {
+        Ok(contents) => {
+            let patterns = ignore::parse_patterns(&contents);
+            if patterns.is_empty() {
+                ignore_stack.clone()
+            } else {
+                ignore_stack.push(path.to_path_buf(), patterns)
+            }
+        }
+        Err(_) => ignore_stack.clone(),
+    }
+}
+
+/// Classifies `path` the way `--type` expects: following `fs::symlink_metadata` so a symlink
+/// isn't silently reported as whatever it points to.
+fn classify(path: &Path) -> FileType {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => FileType::Symlink,
+        Ok(metadata) if metadata.is_dir() => FileType::Directory,
+        _ => FileType::File,
+    }
+}
+
+/// Matches `path`'s own file name against `matcher` (used for `--type d`/`--type l` searches,
+/// where there's no file content to search).
+fn match_by_name(path: &Path, matcher: &Matcher, tx: &mpsc::Sender<Match>) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if matcher.is_match(&name) {
+        let result = Match {
+            path: path.to_path_buf(),
+            location: MatchLocation::Line {
+                line_no: 0,
+                line: name.into_owned(),
+            },
+        };
+        if tx.send(result).is_err() {
+            println!("Error while sending results from the thread");
+        }
+    }
+}
+
+/// Matches each line of `bytes` against `matcher`, decoding lossily so stray non-UTF-8 bytes
+/// (latin-1 text, odd log bytes) don't drop the whole line.
+fn match_text(path: &Path, bytes: &[u8], matcher: &Matcher, tx: &mpsc::Sender<Match>) {
+    for (idx, line_bytes) in bytes.split(|&b| b == b'\n').enumerate() {
+        let line_bytes = line_bytes.strip_suffix(b"\r").unwrap_or(line_bytes);
+        let line = String::from_utf8_lossy(line_bytes);
+        if matcher.is_match(&line) {
+            let result = Match {
+                path: path.to_path_buf(),
+                location: MatchLocation::Line {
+                    line_no: idx + 1,
+                    line: line.into_owned(),
+                },
+            };
+            if tx.send(result).is_err() {
+                println!("Error while sending results from the thread");
+            }
+        }
+    }
+}
+
+/// Matches raw `bytes` against `matcher`, reporting byte offsets instead of lines since binary
+/// content has no meaningful line structure.
+fn match_binary(path: &Path, bytes: &[u8], matcher: &Matcher, tx: &mpsc::Sender<Match>) {
+    for offset in matcher.byte_offsets(bytes) {
+        let result = Match {
+            path: path.to_path_buf(),
+            location: MatchLocation::ByteOffset(offset),
+        };
+        if tx.send(result).is_err() {
+            println!("Error while sending results from the thread");
+        }
+    }
+}
+
+/// Reads `path`'s raw contents and matches them against `matcher`. Files sniffed as binary are
+/// skipped unless `search_binary` opts into searching them (with byte offsets instead of lines).
+fn match_contents(path: &Path, matcher: &Matcher, search_binary: bool, tx: &mpsc::Sender<Match>) {
+    match fs::read(path) {
+        Ok(bytes) => {
+            if is_binary(&bytes) {
+                if search_binary {
+                    match_binary(path, &bytes, matcher, tx);
+                }
+            } else {
+                match_text(path, &bytes, matcher, tx);
+            }
+        }
+        Err(e) => match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                println!("Permission denied: {}", path.to_string_lossy())
+            }
+            _ => println!("Unexpected error while opening a file: {}", e),
+        },
+    }
+}
+
+/// Traversal knobs threaded down to every `process_path` call, bundled together so the worker
+/// functions don't accumulate an ever-growing positional parameter list.
+#[derive(Clone, Copy)]
+struct TraversalOptions {
+    file_type: Option<FileType>,
+    max_depth: Option<usize>,
+    no_ignore: bool,
+    hidden: bool,
+    search_binary: bool,
+}
+
+/// Reads a single work item, either queueing its children (directories, filtered against
+/// `.gitignore` rules, hidden-file rules and `--max-depth`) or matching it against `matcher`
+/// (files by content, directories/symlinks by name) and sending matches through `tx`.
+fn process_path(
+    item: WorkItem,
+    matcher: &Matcher,
+    options: &TraversalOptions,
+    tx: &mpsc::Sender<Match>,
+    queue: &WorkQueue,
+) {
+    let WorkItem {
+        path,
+        ignore_stack,
+        depth,
+    } = item;
+    if classify(&path) == FileType::Directory {
+        if options.file_type == Some(FileType::Directory) {
+            match_by_name(&path, matcher, tx);
+        }
+
+        let child_stack = if options.no_ignore {
+            ignore_stack
+        } else {
+            child_ignore_stack(&path, &ignore_stack)
+        };
+        let child_depth = depth + 1;
+        if options.max_depth.is_some_and(|max_depth| child_depth > max_depth) {
+            return;
+        }
+
+        match fs::read_dir(&path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => {
+                            let name_is_hidden = entry
+                                .file_name()
+                                .to_str()
+                                .map(|name| name.starts_with('.'))
+                                .unwrap_or(false);
+                            if !options.hidden && name_is_hidden {
                                 continue;
-                            } else {
-                                println!("Unexpected error during directory iteration: {}", e);
-                                return;
                             }
+
+                            let entry_path = entry.path();
+                            if !options.no_ignore {
+                                let entry_is_dir =
+                                    entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+                                if child_stack.is_ignored(&entry_path, entry_is_dir) {
+                                    continue;
+                                }
+                            }
+
+                            queue.push(WorkItem {
+                                path: entry_path,
+                                ignore_stack: child_stack.clone(),
+                                depth: child_depth,
+                            });
                         }
-                    },
-                };
-            };
-            for sub_path in paths {
-                let sub_path = match sub_path {
-                    Ok(path) => path,
-                    Err(e) => {
-                        println!(
+                        Err(e) => println!(
                             "Unexpected error happened during directory iteration: {}",
                             e
-                        );
-                        continue;
+                        ),
                     }
-                };
-                let tx = tx.clone();
-                let query = query.clone();
-                let handle = thread::spawn(move || search_in_path(&sub_path.path(), query, tx));
-                handles.push(handle);
+                }
+            }
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::PermissionDenied => {
+                    println!("Permission denied: {}", path.to_string_lossy())
+                }
+                _ => println!("Unexpected error during directory iteration: {}", e),
+            },
+        }
+    } else {
+        match options.file_type {
+            None => match_contents(&path, matcher, options.search_binary, tx),
+            Some(FileType::File) if classify(&path) == FileType::File => {
+                match_contents(&path, matcher, options.search_binary, tx)
+            }
+            Some(FileType::Symlink) if classify(&path) == FileType::Symlink => {
+                match_by_name(&path, matcher, tx)
             }
+            _ => (),
         }
-        for handle in handles {
-            match handle.join() {
-                Ok(_) => (),
-                Err(_) => {
-                    println!("Searching thread panicked")
+    }
+}
+
+/// Searches `path` (and, recursively, its contents) for lines matching `matcher`, using a fixed
+/// pool of `threads` workers that share a work queue instead of spawning a thread per entry. At
+/// most `threads` directories are being read at once, so this can no longer exhaust the open
+/// file descriptor limit the way the old thread-per-entry recursion did. Matches are sent
+/// through `tx` as they're found.
+fn search_in_path(
+    path: PathBuf,
+    matcher: Matcher,
+    threads: usize,
+    options: TraversalOptions,
+    tx: mpsc::Sender<Match>,
+) {
+    let queue = Arc::new(WorkQueue::new(path));
+    let matcher = Arc::new(matcher);
+
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let matcher = Arc::clone(&matcher);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                while let Some(item) = queue.pop() {
+                    process_path(item, &matcher, &options, &tx, &queue);
+                    queue.finish();
                 }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        if handle.join().is_err() {
+            println!("Searching thread panicked")
+        }
+    }
+}
+
+/// Whether the receiver is still holding out for the whole search to finish quickly enough to
+/// print sorted, deterministic output, or has given up and is now printing results live.
+enum ReceiverMode {
+    Buffering,
+    Streaming,
+}
+
+fn format_match(result: &Match, line_number: bool) -> String {
+    match &result.location {
+        MatchLocation::Line { line_no, line } => {
+            if line_number {
+                format!("{}:{}:{}", result.path.to_string_lossy(), line_no, line)
+            } else {
+                format!("{}:{}", result.path.to_string_lossy(), line)
             }
         }
-    } else {
-        // We need this loop because sometimes there will be an os error 24 (too many open file) when searching in big directories
-        // And we will have to wait for some thread to finish and free the resources
-        // If we don't use loop here - some directories won't be scanned due to aforementioned
-        // error and this will result in an undefined behaviour
-        let contents = loop {
-            match fs::read_to_string(&path) {
-                Ok(contents) => break contents,
-                Err(e) => {
-                    match e.kind() {
-                        // There can be a lot of non-utf8 files, so we just skip them
-                        std::io::ErrorKind::InvalidData => return,
-                        std::io::ErrorKind::PermissionDenied => {
-                            println!("Permission denied: {}", path.to_string_lossy());
-                            return;
-                        }
-                        _ => {
-                            // Too many open files -> wait for 1000 nanoseconds and retry
-                            if e.to_string().contains("os error 24") {
-                                std::thread::sleep(std::time::Duration::from_nanos(1000));
-                                continue;
-                            } else {
-                                println!("Unexpected error while opening a file: {}", e);
-                                return;
-                            }
-                        }
-                    };
+        MatchLocation::ByteOffset(offset) => {
+            format!(
+                "{}:(binary match at byte {})",
+                result.path.to_string_lossy(),
+                offset
+            )
+        }
+    }
+}
+
+/// The position used to order matches within the same file: a line number, or a byte offset.
+fn sort_key(result: &Match) -> usize {
+    match &result.location {
+        MatchLocation::Line { line_no, .. } => *line_no,
+        MatchLocation::ByteOffset(offset) => *offset,
+    }
+}
+
+fn print_sorted(buffer: &mut Vec<Match>, line_number: bool) {
+    buffer.sort_by(|a, b| a.path.cmp(&b.path).then(sort_key(a).cmp(&sort_key(b))));
+    for result in buffer.drain(..) {
+        println!("{}", format_match(&result, line_number));
+    }
+}
+
+/// Buffers matches until either the search finishes or `max_buffer_time` elapses, whichever
+/// comes first. On a fast search this prints one sorted, deterministic batch; on a slow one it
+/// flushes whatever was buffered (sorted) and streams the rest as it arrives.
+fn receive_results(rx: mpsc::Receiver<Match>, max_buffer_time: Duration, line_number: bool) {
+    let mut mode = ReceiverMode::Buffering;
+    let mut buffer = Vec::new();
+    let deadline = Instant::now() + max_buffer_time;
+
+    loop {
+        match mode {
+            ReceiverMode::Buffering => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(timeout) {
+                    Ok(result) => buffer.push(result),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        print_sorted(&mut buffer, line_number);
+                        mode = ReceiverMode::Streaming;
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        print_sorted(&mut buffer, line_number);
+                        return;
+                    }
                 }
-            };
-        };
-        if contents.contains(query.as_str()) {
-            match tx.send(path.to_string_lossy().to_string()) {
-                Ok(_) => (),
-                Err(_) => println!("Error while sending results from the thread"),
             }
+            ReceiverMode::Streaming => match rx.recv() {
+                Ok(result) => println!("{}", format_match(&result, line_number)),
+                Err(_) => return,
+            },
         }
-    };
+    }
 }
 
 fn main() {
@@ -147,8 +685,15 @@ fn main() {
         }
     };
     let (tx, rx) = mpsc::channel();
-    search_in_path(&config.path, config.query, tx);
-    for result in rx.iter() {
-        println!("{}", result)
-    }
+    let max_buffer_time = config.max_buffer_time;
+    let line_number = config.line_number;
+    let options = TraversalOptions {
+        file_type: config.file_type,
+        max_depth: config.max_depth,
+        no_ignore: config.no_ignore,
+        hidden: config.hidden,
+        search_binary: config.search_binary,
+    };
+    thread::spawn(move || search_in_path(config.path, config.matcher, config.threads, options, tx));
+    receive_results(rx, max_buffer_time, line_number);
 }